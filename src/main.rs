@@ -1,146 +1,478 @@
 use macroquad::prelude::*;
 
+/// The viewpoint used to follow the aircraft. `Chase` trails behind it,
+/// `Cockpit` sits at the pilot's eye and `Orbit` is a free mouse-driven
+/// inspection camera.
+#[derive(Clone, Copy, PartialEq)]
+enum CameraMode {
+    Chase,
+    Cockpit,
+    Orbit,
+}
+
+/// Length of a single fixed physics step, in seconds.
+const FIXED_DT: f32 = 1. / 120.;
+
+/// The kinematic state advanced by the integrator, free of any rendering or
+/// windowing state so it can be exercised in unit tests.
+#[derive(Clone, Copy)]
+struct PhysicsState {
+    position: Vec3,
+    orientation: glam::Quat,
+    velocity: Vec3,
+    angular_velocity: Vec3,
+}
+
+/// Advance `state` by one fixed `dt` under the given linear and angular
+/// accelerations, applying the altitude ceiling/floor, the ±500 world wrap and
+/// the speed clamps. Pure: same inputs always yield the same output.
+fn integrate(
+    mut state: PhysicsState,
+    acceleration: Vec3,
+    angular_acceleration: Vec3,
+    dt: f32,
+) -> PhysicsState {
+    state.angular_velocity += angular_acceleration * dt;
+    state.velocity += acceleration * dt;
+
+    if state.position.y <= 1. && state.velocity.y < 0. {
+        state.velocity.y = 0.;
+    }
+    if state.position.y >= 49. && state.velocity.y > 0. {
+        state.velocity.y = 0.;
+    }
+    if state.position.x >= 500. {
+        state.position.x -= 1000.;
+    } else if state.position.x <= -500. {
+        state.position.x += 1000.;
+    }
+    if state.position.z >= 500. {
+        state.position.z -= 1000.;
+    } else if state.position.z <= -500. {
+        state.position.z += 1000.;
+    }
+
+    state.velocity = state.velocity.clamp_length_max(50.);
+    state.angular_velocity = state.angular_velocity.clamp_length_max(5.);
+
+    state.position += state.velocity * dt;
+    let w = state.angular_velocity;
+    let spin = glam::Quat::from_xyzw(w.x, w.y, w.z, 0.) * state.orientation;
+    state.orientation =
+        glam::Quat::from_vec4(glam::Vec4::from(state.orientation) + glam::Vec4::from(spin) * 0.5 * dt)
+            .normalize();
+    state.angular_velocity *= 0.99;
+    state
+}
+
 struct Plane {
-    head: Vec3,
-    center: Vec3,
-    right_wing_tip: Vec3,
+    position: Vec3,
+    orientation: glam::Quat,
     velocity: Vec3,
     angular_velocity: Vec3,
     acceleration: Vec3,
+    // Physics state at the end of the previous fixed step, used to interpolate
+    // the rendered pose.
+    previous: PhysicsState,
+    // Body-space inertia tensor, computed once from the mesh triangles.
+    inertia: glam::Mat3,
+    // Air density and tangential skin-drag coefficient, tunable to adjust
+    // stall and damping behaviour.
+    rho: f32,
+    skin_drag: f32,
     mesh: macroquad::models::Mesh,
     camera: Camera3D,
+    camera_mode: CameraMode,
+    // Orbit-camera state: euler angles (radians) and distance to the plane.
+    orbit_azimuth: f32,
+    orbit_altitude: f32,
+    orbit_radius: f32,
 }
 
 impl Plane {
-    fn new(mesh: Mesh) -> Self {
+    fn new(mut mesh: Mesh) -> Self {
+        // Re-centre the local mesh on its centroid so `position` tracks the
+        // centre of mass and `mesh.vertices` stay in fixed body/local space.
+        let center = _get_center_from_vertices(&mesh.vertices);
+        for vertex in &mut mesh.vertices {
+            vertex.position -= center;
+        }
+        let inertia = _get_inertia_tensor_from_mesh(&mesh.vertices, &mesh.indices);
         let mut plane = Self {
-            head: _get_head_from_vertices(&mesh.vertices),
-            center: _get_center_from_vertices(&mesh.vertices),
-            right_wing_tip: _get_right_wing_tip_from_vertices(&mesh.vertices),
+            position: center,
+            orientation: glam::Quat::IDENTITY,
             velocity: Vec3::ZERO,
             angular_velocity: Vec3::ZERO,
             acceleration: Vec3::ZERO,
+            previous: PhysicsState {
+                position: center,
+                orientation: glam::Quat::IDENTITY,
+                velocity: Vec3::ZERO,
+                angular_velocity: Vec3::ZERO,
+            },
+            inertia,
+            rho: 0.1,
+            skin_drag: 0.02,
             mesh,
             camera: Camera3D {
                 ..Default::default()
             },
+            camera_mode: CameraMode::Chase,
+            orbit_azimuth: 0.,
+            orbit_altitude: 0.5,
+            orbit_radius: 6.,
         };
-        plane.camera.position = plane.up() * 2. + plane.forward() * -2.;
-        plane.camera.target = plane.forward() * 3.;
-        plane.camera.up = plane.up();
+        let (position, target, up) = plane.desired_camera();
+        plane.camera.position = position;
+        plane.camera.target = target;
+        plane.camera.up = up;
         plane
     }
-    fn draw(&self) {
-        draw_mesh(&self.mesh);
-    }
-    fn translate_by(&mut self, move_vector: Vec3) {
-        for vertex in &mut self.mesh.vertices {
-            vertex.position += move_vector;
-        }
-        self.head += move_vector;
-        self.right_wing_tip += move_vector;
-        self.center = _get_center_from_vertices(&self.mesh.vertices);
-        let temp = _get_camera_vectors(&self);
-        self.camera.position = temp.0;
-        self.camera.target = temp.1;
-        self.camera.up = temp.2;
-    }
-
-    fn rotate_by_axis_angle(&mut self, axis: &Vec3, angle: f32) {
-        let rotation_matrix = glam::Mat3::from_axis_angle(*axis, angle);
-        for vertex in &mut self.mesh.vertices {
-            vertex.position = rotation_matrix.mul_vec3(vertex.position - self.center) + self.center;
-        }
-        self.head = rotation_matrix.mul_vec3(self.head - self.center) + self.center;
-        self.right_wing_tip =
-            rotation_matrix.mul_vec3(self.right_wing_tip - self.center) + self.center;
-        self.center = _get_center_from_vertices(&self.mesh.vertices);
-        let temp = _get_camera_vectors(&self);
-        self.camera.position = temp.0;
-        self.camera.target = temp.1;
-        self.camera.up = temp.2;
-    }
-
-    fn rotate_by(&mut self, rotate_vector: Vec3) {
-        self.rotate_by_axis_angle(&self.forward(), rotate_vector.x);
-        self.rotate_by_axis_angle(&self.up(), rotate_vector.y);
-        self.rotate_by_axis_angle(&self.right(), rotate_vector.z);
+    fn draw(&self, alpha: f32) {
+        // Interpolate between the last two physics states so the rendered pose
+        // stays smooth even though the simulation runs at a fixed rate.
+        let position = self.previous.position.lerp(self.position, alpha);
+        let orientation = self.previous.orientation.slerp(self.orientation, alpha);
+        // Transform the body-space vertices by the model matrix into a scratch
+        // copy; the stored mesh itself never leaves local space.
+        let model = glam::Mat4::from_rotation_translation(orientation, position);
+        let vertices = self
+            .mesh
+            .vertices
+            .iter()
+            .map(|vertex| macroquad::models::Vertex {
+                position: model.transform_point3(vertex.position),
+                ..*vertex
+            })
+            .collect::<Vec<macroquad::models::Vertex>>();
+        draw_mesh(&Mesh {
+            vertices,
+            indices: self.mesh.indices.clone(),
+            texture: self.mesh.texture.clone(),
+        });
     }
 
-    fn get_aerodynamic_force_and_torque(&self) -> (Vec3, Vec3) {
+    fn get_aerodynamic_force_and_torque(&self, wind: &Vec3) -> (Vec3, Vec3) {
         let mut res_force = Vec3::ZERO;
         let mut res_torque = Vec3::ZERO;
+        // Rotate the body-space triangles into world space; `position` cancels
+        // out because centroids are taken relative to the centre of mass.
+        let rotation = self.orientation;
         for [i, j, k] in self.mesh.indices.chunks_exact(3).map(|index_group| {
             [
-                self.mesh.vertices[index_group[0] as usize].position - self.center,
-                self.mesh.vertices[index_group[1] as usize].position - self.center,
-                self.mesh.vertices[index_group[2] as usize].position - self.center,
+                rotation * self.mesh.vertices[index_group[0] as usize].position,
+                rotation * self.mesh.vertices[index_group[1] as usize].position,
+                rotation * self.mesh.vertices[index_group[2] as usize].position,
             ]
         }) {
             let side1 = i - j;
             let side2 = j - k;
-            let centroid = (i + j + k) / 3.;
+            let r = (i + j + k) / 3.;
 
             let normal = side1.cross(side2).normalize();
             let area = (side1.cross(side2) * 0.5).length();
-            let tangential_velocity = self.velocity - (self.velocity.dot(normal)) * normal;
-            let force = tangential_velocity.length().powi(2) * area * normal;
-            let torque = -centroid.cross(force) * centroid.length_recip().powi(2);
 
-            res_torque += torque;
+            // Airflow seen by this face, including the contribution of the
+            // body's own rotation about the centre of mass.
+            let v_local = -(self.velocity + self.angular_velocity.cross(r) + *wind);
+            let vn = v_local.dot(normal);
+
+            // Normal pressure only acts on windward faces; tangential term is
+            // a skin-drag along the face.
+            let pressure = self.rho * area * vn.max(0.).powi(2) * normal;
+            let skin = self.skin_drag * self.rho * area * (v_local - vn * normal);
+            let force = pressure + skin;
+
             res_force += force;
+            res_torque += r.cross(force);
         }
-        (
-            res_force * 0.1,
-            Vec3::ZERO,
-            // mat3(self.forward(), self.up(), self.right()).mul_vec3(res_torque) * 0.001,
-        )
+        (res_force, res_torque)
     }
 
-    fn update(&mut self, dt: f32, thrust: &Vec3, torque: &Vec3, wind: &Vec3, gravity: &Vec3) {
-        let (aerodynamic_force, aerodynamic_torque) = self.get_aerodynamic_force_and_torque();
-        self.acceleration = aerodynamic_force + *thrust + *wind + *gravity;
-        let angular_acceleration = aerodynamic_torque + *torque;
-        self.angular_velocity += angular_acceleration * dt;
-        self.velocity += self.acceleration * dt;
-        if self.center.y <= 1. && self.velocity.y < 0. {
-            self.velocity.y = 0.;
-        }
-        if self.center.y >= 49. && self.velocity.y > 0. {
-            self.velocity.y = 0.;
-        }
-        if self.center.x >= 500. {
-            self.translate_by(Vec3::X * -1000.);
-        }
-        if self.center.x <= -500. {
-            self.translate_by(Vec3::X * 1000.);
+    /// Angular acceleration produced by a world-space `torque`, resolving the
+    /// body inertia tensor into world space (`I_world = R * I * R^T`).
+    fn angular_acceleration(&self, torque: Vec3) -> Vec3 {
+        let rotation = glam::Mat3::from_quat(self.orientation);
+        let inertia_world = rotation * self.inertia * rotation.transpose();
+        inertia_world.inverse() * torque
+    }
+
+    /// Advance the simulation by one fixed physics step. The kinematic part is
+    /// delegated to the pure [`integrate`] function so it can be tested without
+    /// a window; terrain collision is resolved afterwards.
+    fn step(
+        &mut self,
+        dt: f32,
+        thrust: &Vec3,
+        torque: &Vec3,
+        wind: &Vec3,
+        gravity: &Vec3,
+        terrain: &Terrain,
+    ) {
+        self.previous = self.state();
+        let (aerodynamic_force, aerodynamic_torque) = self.get_aerodynamic_force_and_torque(wind);
+        self.acceleration = aerodynamic_force + *thrust + *gravity;
+        let angular_acceleration = self.angular_acceleration(aerodynamic_torque + *torque);
+        let next = integrate(self.state(), self.acceleration, angular_acceleration, dt);
+        self.position = next.position;
+        self.orientation = next.orientation;
+        self.velocity = next.velocity;
+        self.angular_velocity = next.angular_velocity;
+        self.resolve_terrain_collision(terrain);
+    }
+
+    fn state(&self) -> PhysicsState {
+        PhysicsState {
+            position: self.position,
+            orientation: self.orientation,
+            velocity: self.velocity,
+            angular_velocity: self.angular_velocity,
         }
-        if self.center.z >= 500. {
-            self.translate_by(Vec3::Z * -1000.);
+    }
+
+    /// Viewpoint the active mode wants this frame, as `(position, target, up)`.
+    fn desired_camera(&self) -> (Vec3, Vec3, Vec3) {
+        match self.camera_mode {
+            CameraMode::Chase => (
+                self.position + self.up() * 2. + self.forward() * -2.,
+                self.position + self.forward() * 3.,
+                self.up(),
+            ),
+            CameraMode::Cockpit => (
+                self.position + self.up() * 0.5,
+                self.position + self.forward() * 3.,
+                self.up(),
+            ),
+            CameraMode::Orbit => {
+                let (sin_alt, cos_alt) = self.orbit_altitude.sin_cos();
+                let (sin_azi, cos_azi) = self.orbit_azimuth.sin_cos();
+                let offset = vec3(cos_alt * -sin_azi, sin_alt, cos_alt * cos_azi);
+                (self.position + offset * self.orbit_radius, self.position, Vec3::Y)
+            }
         }
-        if self.center.z <= -500. {
-            self.translate_by(Vec3::Z * 1000.);
+    }
+
+    /// Drive the orbit angles from mouse input and ease the camera toward the
+    /// mode's desired pose with exponential smoothing.
+    fn update_camera(&mut self, dt: f32, mouse_delta: Vec2, wheel: f32) {
+        if self.camera_mode == CameraMode::Orbit {
+            const LIMIT: f32 = 80. * std::f32::consts::PI / 180.;
+            self.orbit_azimuth += mouse_delta.x;
+            self.orbit_altitude = (self.orbit_altitude + mouse_delta.y).clamp(-LIMIT, LIMIT);
+            self.orbit_radius = (self.orbit_radius - wheel).clamp(2., 50.);
         }
-        self.velocity = self.velocity.clamp_length_max(50.);
-        self.angular_velocity = self.angular_velocity.clamp_length_max(5.);
-        self.translate_by(self.velocity * dt);
-        self.rotate_by(self.angular_velocity * dt);
-        self.angular_velocity *= 0.99;
+        let (position, target, up) = self.desired_camera();
+        let t = 1. - (-8. * dt).exp();
+        self.camera.position = self.camera.position.lerp(position, t);
+        self.camera.target = self.camera.target.lerp(target, t);
+        self.camera.up = up;
     }
+
     fn up(&self) -> Vec3 {
-        (self.right_wing_tip - self.center)
-            .cross(self.head - self.center)
-            .normalize()
+        self.orientation * Vec3::Y
     }
     fn forward(&self) -> Vec3 {
-        (self.head - self.center).normalize()
+        self.orientation * Vec3::X
     }
     fn backward(&self) -> Vec3 {
         -self.forward()
     }
     fn right(&self) -> Vec3 {
-        (self.right_wing_tip - self.center).normalize()
+        self.orientation * Vec3::Z
+    }
+
+    /// Push the plane out of the terrain along the surface normal and cancel
+    /// the inbound velocity component at each sampled body point. Returns
+    /// `true` when any impact along the normal exceeds the crash threshold.
+    fn resolve_terrain_collision(&mut self, terrain: &Terrain) -> bool {
+        let samples = [
+            Vec3::ZERO,
+            self.right() * 2.,
+            self.right() * -2.,
+            self.forward() * 2.,
+        ];
+        let mut crashed = false;
+        for offset in samples {
+            let point = self.position + offset;
+            let ground = terrain.height_at(point.x, point.z);
+            if point.y < ground {
+                let normal = terrain.normal_at(point.x, point.z);
+                self.position += normal * (ground - point.y);
+                let vn = self.velocity.dot(normal);
+                if vn < 0. {
+                    self.velocity -= vn * normal;
+                    if vn < -20. {
+                        crashed = true;
+                    }
+                }
+            }
+        }
+        crashed
+    }
+}
+
+/// Half-size of a terrain tile; it matches the ±500 world-wrap region so the
+/// plane always has ground beneath it.
+const TERRAIN_EXTENT: f32 = 500.;
+/// Number of grid cells along each axis of a tile.
+const TERRAIN_RESOLUTION: usize = 100;
+
+/// A single streamed heightmap tile. The grid of sampled heights is kept so
+/// collision can interpolate the surface without touching the render mesh.
+struct Terrain {
+    // World-space XZ of the tile's lower corner.
+    origin: Vec2,
+    cell: f32,
+    heights: Vec<f32>,
+    mesh: Mesh,
+}
+
+impl Terrain {
+    fn new(center: Vec3) -> Self {
+        let origin = Self::origin_for(center);
+        let cell = 2. * TERRAIN_EXTENT / TERRAIN_RESOLUTION as f32;
+        let (heights, mesh) = Self::generate(origin, cell);
+        Self {
+            origin,
+            cell,
+            heights,
+            mesh,
+        }
+    }
+
+    fn origin_for(center: Vec3) -> Vec2 {
+        vec2(center.x - TERRAIN_EXTENT, center.z - TERRAIN_EXTENT)
+    }
+
+    /// Re-stream the tile recentred on `center` once the plane has wandered
+    /// into the outer half of the current tile (which includes a wrap jump).
+    fn follow(&mut self, center: Vec3) {
+        let tile_center = self.origin + Vec2::splat(TERRAIN_EXTENT);
+        if (vec2(center.x, center.z) - tile_center).length() > TERRAIN_EXTENT * 0.5 {
+            self.origin = Self::origin_for(center);
+            let (heights, mesh) = Self::generate(self.origin, self.cell);
+            self.heights = heights;
+            self.mesh = mesh;
+        }
+    }
+
+    fn generate(origin: Vec2, cell: f32) -> (Vec<f32>, Mesh) {
+        let n = TERRAIN_RESOLUTION;
+        let stride = n + 1;
+        let mut heights = vec![0.; stride * stride];
+        let mut vertices = Vec::with_capacity(stride * stride);
+        for zi in 0..=n {
+            for xi in 0..=n {
+                let wx = origin.x + xi as f32 * cell;
+                let wz = origin.y + zi as f32 * cell;
+                let h = _terrain_fbm(wx, wz);
+                heights[zi * stride + xi] = h;
+                // Per-vertex normal from the noise gradient.
+                let normal = vec3(
+                    _terrain_fbm(wx - cell, wz) - _terrain_fbm(wx + cell, wz),
+                    2. * cell,
+                    _terrain_fbm(wx, wz - cell) - _terrain_fbm(wx, wz + cell),
+                )
+                .normalize();
+                let shade = (0.4 + 0.6 * normal.y).clamp(0., 1.);
+                vertices.push(macroquad::models::Vertex {
+                    position: vec3(wx, h, wz),
+                    uv: vec2(xi as f32 / n as f32, zi as f32 / n as f32),
+                    color: Color::new(0.25 * shade, 0.6 * shade, 0.25 * shade, 1.),
+                });
+            }
+        }
+        let mut indices = Vec::with_capacity(n * n * 6);
+        for zi in 0..n {
+            for xi in 0..n {
+                let tl = (zi * stride + xi) as u16;
+                let tr = tl + 1;
+                let bl = tl + stride as u16;
+                let br = bl + 1;
+                indices.extend_from_slice(&[tl, bl, tr, tr, bl, br]);
+            }
+        }
+        (
+            heights,
+            Mesh {
+                vertices,
+                indices,
+                texture: None,
+            },
+        )
+    }
+
+    fn draw(&self) {
+        draw_mesh(&self.mesh);
+    }
+
+    /// Bilinearly interpolated terrain height at a world XZ position.
+    fn height_at(&self, x: f32, z: f32) -> f32 {
+        let n = TERRAIN_RESOLUTION;
+        let stride = n + 1;
+        let gx = (x - self.origin.x) / self.cell;
+        let gz = (z - self.origin.y) / self.cell;
+        let x0 = (gx.floor() as isize).clamp(0, n as isize - 1) as usize;
+        let z0 = (gz.floor() as isize).clamp(0, n as isize - 1) as usize;
+        let fx = (gx - x0 as f32).clamp(0., 1.);
+        let fz = (gz - z0 as f32).clamp(0., 1.);
+        let at = |xi: usize, zi: usize| self.heights[zi * stride + xi];
+        let top = at(x0, z0) + (at(x0 + 1, z0) - at(x0, z0)) * fx;
+        let bottom = at(x0, z0 + 1) + (at(x0 + 1, z0 + 1) - at(x0, z0 + 1)) * fx;
+        top + (bottom - top) * fz
+    }
+
+    /// Surface normal from central differences of the interpolated height.
+    fn normal_at(&self, x: f32, z: f32) -> Vec3 {
+        let e = self.cell;
+        vec3(
+            self.height_at(x - e, z) - self.height_at(x + e, z),
+            2. * e,
+            self.height_at(x, z - e) - self.height_at(x, z + e),
+        )
+        .normalize()
+    }
+}
+
+/// Deterministic hash of integer lattice coordinates into `[0, 1]`.
+fn _terrain_hash(x: i32, z: i32) -> f32 {
+    let mut h = (x as u32)
+        .wrapping_mul(374761393)
+        .wrapping_add((z as u32).wrapping_mul(668265263));
+    h = (h ^ (h >> 13)).wrapping_mul(1274126177);
+    (h ^ (h >> 16)) as f32 / u32::MAX as f32
+}
+
+/// Smooth value noise built by interpolating lattice hashes.
+fn _terrain_value_noise(x: f32, z: f32) -> f32 {
+    let (ix, iz) = (x.floor(), z.floor());
+    let (fx, fz) = (x - ix, z - iz);
+    let smooth = |t: f32| t * t * (3. - 2. * t);
+    let (u, v) = (smooth(fx), smooth(fz));
+    let (ix, iz) = (ix as i32, iz as i32);
+    let n00 = _terrain_hash(ix, iz);
+    let n10 = _terrain_hash(ix + 1, iz);
+    let n01 = _terrain_hash(ix, iz + 1);
+    let n11 = _terrain_hash(ix + 1, iz + 1);
+    let nx0 = n00 + (n10 - n00) * u;
+    let nx1 = n01 + (n11 - n01) * u;
+    nx0 + (nx1 - nx0) * v
+}
+
+/// Multi-octave fractional Brownian motion giving the terrain height in world
+/// units.
+fn _terrain_fbm(x: f32, z: f32) -> f32 {
+    let mut amplitude = 1.;
+    let mut frequency = 1. / 128.;
+    let mut sum = 0.;
+    let mut total = 0.;
+    for _ in 0..4 {
+        sum += amplitude * _terrain_value_noise(x * frequency, z * frequency);
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.;
     }
+    sum / total * 40.
 }
 
 fn _get_vertices_from_mesh(mesh: &tobj::Mesh) -> Vec<macroquad::models::Vertex> {
@@ -158,22 +490,26 @@ fn _get_vertices_from_mesh(mesh: &tobj::Mesh) -> Vec<macroquad::models::Vertex>
         .collect::<Vec<macroquad::models::Vertex>>()
 }
 
-fn _get_head_from_vertices(vertices: &Vec<macroquad::models::Vertex>) -> macroquad::math::Vec3 {
-    vertices
-        .iter()
-        .map(|vertex| vertex.position)
-        .max_by(|this, other| this.x.partial_cmp(&other.x).unwrap())
-        .unwrap()
-}
-
-fn _get_right_wing_tip_from_vertices(
+fn _get_inertia_tensor_from_mesh(
     vertices: &Vec<macroquad::models::Vertex>,
-) -> macroquad::math::Vec3 {
-    vertices
-        .iter()
-        .map(|vertex| vertex.position)
-        .max_by_key(|position| (position.z * 100.) as usize)
-        .unwrap()
+    indices: &Vec<u16>,
+) -> glam::Mat3 {
+    // Treat each triangle as a thin plate whose mass is proportional to its
+    // area, lumped at its centroid. `I = sum m (|r|^2 * Id - r (x) r)`.
+    let mut inertia = glam::Mat3::ZERO;
+    for index_group in indices.chunks_exact(3) {
+        let i = vertices[index_group[0] as usize].position;
+        let j = vertices[index_group[1] as usize].position;
+        let k = vertices[index_group[2] as usize].position;
+        let r = (i + j + k) / 3.;
+        let area = ((i - j).cross(j - k) * 0.5).length();
+        inertia += area * (r.length_squared() * glam::Mat3::IDENTITY - glam::Mat3::from_cols(
+            r * r.x,
+            r * r.y,
+            r * r.z,
+        ));
+    }
+    inertia
 }
 
 fn _get_center_from_vertices(vertices: &Vec<macroquad::models::Vertex>) -> Vec3 {
@@ -185,14 +521,6 @@ fn _get_center_from_vertices(vertices: &Vec<macroquad::models::Vertex>) -> Vec3
         / (vertices.len() as f32)
 }
 
-fn _get_camera_vectors(plane: &Plane) -> (Vec3, Vec3, Vec3) {
-    (
-        plane.center + plane.up() * 2. + plane.forward() * -2.,
-        plane.center + plane.forward() * 3.,
-        plane.up(),
-    )
-}
-
 fn load_model() -> Mesh {
     let (models, _materials) = tobj::load_obj(
         "assets/plane.obj",
@@ -221,6 +549,90 @@ fn pretty_vector(vector: &Vec3) -> String {
     format!("[{:.2}, {:.2}, {:.2}]", vector.x, vector.y, vector.z)
 }
 
+/// A device-independent flight command. Rotation channels live in `[-1, 1]`
+/// and throttle/brake in `[0, 1]`; both keyboard and gamepad produce this so
+/// a single code path turns it into thrust and torque.
+#[derive(Default)]
+struct FlightCommand {
+    pitch: f32,
+    roll: f32,
+    yaw: f32,
+    thrust: f32,
+    brake: f32,
+}
+
+const DEADZONE: f32 = 0.15;
+
+/// Squared response curve with a deadzone, so small stick corrections stay
+/// gentle while the sign of the deflection is preserved.
+fn shape_axis(x: f32) -> f32 {
+    if x.abs() < DEADZONE {
+        0.
+    } else {
+        x.signum() * x * x
+    }
+}
+
+fn keyboard_command() -> FlightCommand {
+    let mut command = FlightCommand::default();
+    if is_key_down(KeyCode::W) {
+        command.thrust = 1.;
+    }
+    if is_key_down(KeyCode::S) {
+        command.brake = 1.;
+    }
+    if is_key_down(KeyCode::Left) {
+        command.yaw = 1.;
+    }
+    if is_key_down(KeyCode::Right) {
+        command.yaw = -1.;
+    }
+    if is_key_down(KeyCode::Up) {
+        command.pitch = 1.;
+    }
+    if is_key_down(KeyCode::Down) {
+        command.pitch = -1.;
+    }
+    if is_key_down(KeyCode::A) {
+        command.roll = -1.;
+    }
+    if is_key_down(KeyCode::D) {
+        command.roll = 1.;
+    }
+    command
+}
+
+/// Read the pad in slot 0, mapping the left stick to pitch/roll, the right
+/// stick X to yaw and the analog triggers to thrust/brake. Returns `None`
+/// when that pad is not connected so the keyboard path can take over; other
+/// slots are not read.
+fn gamepad_command(pads: &mut quad_gamepad::ControllerContext) -> Option<FlightCommand> {
+    pads.update();
+    let pad = pads.info(0);
+    if pad.status != quad_gamepad::ControllerStatus::Connected {
+        return None;
+    }
+    // Analog axes: [lx, ly, rx, ry, lt, rt]; triggers travel in [0, 1].
+    let axes = pad.analog_state;
+    Some(FlightCommand {
+        pitch: shape_axis(-axes[1]),
+        roll: shape_axis(axes[0]),
+        yaw: shape_axis(axes[2]),
+        thrust: axes[5].max(0.),
+        brake: axes[4].max(0.),
+    })
+}
+
+/// Collapse a `FlightCommand` into the proportional `(thrust, torque)` pair
+/// the integrator expects, resolved against the plane's current body axes.
+fn command_to_thrust_torque(plane: &Plane, command: &FlightCommand) -> (Vec3, Vec3) {
+    let thrust = plane.forward() * command.thrust * 2. + plane.backward() * command.brake * 0.5;
+    let torque =
+        (plane.forward() * command.roll + plane.up() * command.yaw + plane.right() * command.pitch)
+            * 0.1;
+    (thrust, torque)
+}
+
 #[macroquad::main("Flight Simulator")]
 async fn main() {
     let mut plane: Plane = Plane::new(load_model());
@@ -228,48 +640,43 @@ async fn main() {
     let gravity = Vec3::Y * -0.5;
     let wind = Vec3::ZERO;
 
-    loop {
-        let dt = get_frame_time();
+    let mut pads = quad_gamepad::ControllerContext::new().unwrap();
+    let mut terrain = Terrain::new(plane.position);
+    let mut accumulator = 0.;
 
-        clear_background(LIGHTGRAY);
-        draw_grid(1000, 2.0, RED, GREEN);
-
-        let mut thrust = Vec3::ZERO;
-        let mut torque = Vec3::ZERO;
+    loop {
+        clear_background(SKYBLUE);
 
         if is_key_down(KeyCode::Escape) {
             break;
         }
-        if is_key_down(KeyCode::W) {
-            thrust += plane.forward() * 2.;
-        }
-        if is_key_down(KeyCode::S) {
-            thrust += plane.backward() * 0.5;
-        }
-        if is_key_down(KeyCode::Left) {
-            torque.y = 0.1;
-        }
-        if is_key_down(KeyCode::Right) {
-            torque.y = -0.1;
-        }
-        if is_key_down(KeyCode::Up) {
-            torque.z = 0.1;
-        }
-        if is_key_down(KeyCode::Down) {
-            torque.z = -0.1;
-        }
-        if is_key_down(KeyCode::A) {
-            torque.x = -0.1;
-        }
-        if is_key_down(KeyCode::D) {
-            torque.x = 0.1;
+        if is_key_pressed(KeyCode::C) {
+            plane.camera_mode = match plane.camera_mode {
+                CameraMode::Chase => CameraMode::Cockpit,
+                CameraMode::Cockpit => CameraMode::Orbit,
+                CameraMode::Orbit => CameraMode::Chase,
+            };
         }
 
-        plane.update(dt, &thrust, &torque, &wind, &gravity);
+        // Prefer a connected gamepad, falling back to the keyboard; both feed
+        // the same command-to-force path.
+        let command = gamepad_command(&mut pads).unwrap_or_else(keyboard_command);
+        let (thrust, torque) = command_to_thrust_torque(&plane, &command);
+
+        // Run zero or more fixed sub-steps for the accumulated real time,
+        // carrying the remainder so the physics stays frame-rate independent.
+        accumulator += get_frame_time();
+        while accumulator >= FIXED_DT {
+            plane.step(FIXED_DT, &thrust, &torque, &wind, &gravity, &terrain);
+            terrain.follow(plane.position);
+            accumulator -= FIXED_DT;
+        }
+        let alpha = accumulator / FIXED_DT;
+        plane.update_camera(get_frame_time(), mouse_delta_position(), mouse_wheel().1);
 
         set_default_camera();
         draw_text(
-            &("Position    : ".to_owned() + &pretty_vector(&plane.center)),
+            &("Position    : ".to_owned() + &pretty_vector(&plane.position)),
             20.,
             30.,
             20.,
@@ -298,7 +705,48 @@ async fn main() {
         );
 
         set_camera(&plane.camera);
-        plane.draw();
+        terrain.draw();
+        plane.draw(alpha);
         next_frame().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_at(position: Vec3, velocity: Vec3) -> PhysicsState {
+        PhysicsState {
+            position,
+            orientation: glam::Quat::IDENTITY,
+            velocity,
+            angular_velocity: Vec3::ZERO,
+        }
+    }
+
+    #[test]
+    fn velocity_is_clamped_to_50() {
+        // A huge acceleration must not push the speed past the clamp.
+        let start = state_at(vec3(0., 25., 0.), Vec3::ZERO);
+        let next = integrate(start, Vec3::X * 10_000., Vec3::ZERO, FIXED_DT);
+        assert!(next.velocity.length() <= 50.0001);
+    }
+
+    #[test]
+    fn downward_velocity_is_zeroed_at_the_floor() {
+        // A plane at y <= 1 moving down has its vertical velocity zeroed.
+        let start = state_at(vec3(0., 1., 0.), vec3(0., -10., 0.));
+        let next = integrate(start, Vec3::ZERO, Vec3::ZERO, FIXED_DT);
+        assert_eq!(next.velocity.y, 0.);
+    }
+
+    #[test]
+    fn crossing_x_boundary_wraps_by_1000() {
+        let start = state_at(vec3(500., 25., 0.), Vec3::ZERO);
+        let next = integrate(start, Vec3::ZERO, Vec3::ZERO, FIXED_DT);
+        // Position wraps by exactly -1000 on x; velocity and orientation stay.
+        assert!((next.position.x - start.position.x + 1000.).abs() < 1e-3);
+        assert_eq!(next.velocity, start.velocity);
+        assert_eq!(next.orientation, start.orientation);
+    }
+}